@@ -21,9 +21,10 @@ use std::sync::Arc;
 use byteorder::{LittleEndian, ByteOrder};
 
 use evm;
+use evm::CallType;
 
 use parity_wasm::interpreter;
-use util::{H256, Address};
+use util::{H256, Address, U256};
 
 use super::ptr::{WasmPtr, Error as PtrError};
 use super::call_args::CallArgs;
@@ -39,10 +40,85 @@ pub enum Error {
 	InvalidGasState,
 	/// Memory access violation
 	AccessViolation,
+	/// Gas limit exceeded
+	GasLimit,
 	/// Interpreter runtime error
 	Interpreter(interpreter::Error),
 }
 
+/// Gas schedule for wasm contract execution.
+///
+/// The injected `gas` host function only accounts for a flat block count chosen by
+/// the bytecode transform; this table prices the runtime-side operations (allocation,
+/// storage, memory copies, logs) that the transform cannot see, so wasm costs line up
+/// with the equivalent EVM opcodes. Every field here is read somewhere in `Runtime` --
+/// don't add a knob here without wiring a matching `charge()` call.
+#[derive(Clone, Debug)]
+pub struct WasmCosts {
+	/// Gas charged per byte of dynamic memory allocated through `malloc`/`alloc`.
+	pub alloc: u32,
+	/// Gas charged per byte `write_descriptor` writes into contract memory (the call
+	/// descriptor and the call arguments). Other memory copies do not charge it.
+	pub mem_copy: u32,
+	/// Gas charged per storage read (`SLOAD`-equivalent).
+	pub sload: u32,
+	/// Gas charged per storage write (`SSTORE`-equivalent).
+	pub sstore: u32,
+	/// Gas charged per log topic (`LOG0`-`LOG4`-equivalent).
+	pub log_topic: u32,
+	/// Gas charged per byte of log data.
+	pub log_data: u32,
+	/// Gas charged per environment/blockchain query (`NUMBER`, `TIMESTAMP`, `COINBASE`,
+	/// `DIFFICULTY`, `GASLIMIT`, `GAS`-equivalent).
+	pub env_info: u32,
+	/// Gas charged per balance query (`BALANCE`-equivalent).
+	pub balance: u32,
+	/// Gas charged per blockhash query (`BLOCKHASH`-equivalent).
+	pub blockhash: u32,
+}
+
+impl Default for WasmCosts {
+	fn default() -> Self {
+		WasmCosts {
+			alloc: 16,
+			mem_copy: 1,
+			sload: 200,
+			sstore: 20000,
+			log_topic: 375,
+			log_data: 8,
+			env_info: 2,
+			balance: 400,
+			blockhash: 20,
+		}
+	}
+}
+
+/// Add `amount` to `counter`, erroring on overflow or on crossing `limit`. Pulled out of
+/// `Runtime::charge` so the accounting can be unit tested without a `MemoryInstance`/`Ext`
+/// to build a full `Runtime` around.
+fn checked_charge(counter: u64, limit: u64, amount: u64) -> Result<u64, Error> {
+	match counter.checked_add(amount) {
+		Some(next) if next <= limit => Ok(next),
+		_ => Err(Error::GasLimit),
+	}
+}
+
+/// Subtract `amount` from `counter`, saturating at zero. Pulled out of `Runtime::refund`
+/// for the same testability reason as `checked_charge`.
+fn checked_refund(counter: u64, amount: u64) -> u64 {
+	counter.saturating_sub(amount)
+}
+
+/// Add `amount` to `top`, erroring on overflow or on crossing `memory_len`. Pulled out of
+/// `Runtime::alloc` for the same testability reason as `checked_charge`.
+fn checked_alloc_top(top: u32, amount: u32, memory_len: usize) -> Result<u32, Error> {
+	let new_top = top.checked_add(amount).ok_or(Error::Allocator)?;
+	if new_top as usize > memory_len {
+		return Err(Error::Allocator);
+	}
+	Ok(new_top)
+}
+
 impl From<interpreter::Error> for Error {
 	fn from(err: interpreter::Error) -> Self {
 		Error::Interpreter(err)
@@ -61,6 +137,9 @@ pub struct Runtime<'a> {
 	gas_counter: u64,
 	gas_limit: u64,
 	dynamic_top: u32,
+	costs: WasmCosts,
+	result: Vec<u8>,
+	should_revert: bool,
 	ext: &'a mut evm::Ext,
 	memory: Arc<interpreter::MemoryInstance>,
 }
@@ -68,19 +147,44 @@ pub struct Runtime<'a> {
 impl<'a> Runtime<'a> {
 	pub fn with_params<'b>(
 		ext: &'b mut evm::Ext,
-		memory: Arc<interpreter::MemoryInstance>, 
-		stack_space: u32, 
+		memory: Arc<interpreter::MemoryInstance>,
+		stack_space: u32,
 		gas_limit: u64,
+		costs: WasmCosts,
 	) -> Runtime<'b> {
 		Runtime {
 			gas_counter: 0,
 			gas_limit: gas_limit,
 			dynamic_top: stack_space,
+			costs: costs,
+			result: Vec::new(),
+			should_revert: false,
 			memory: memory,
 			ext: ext,
 		}
 	}
 
+	/// Charge `amount` of gas against the running total, erroring out when that would
+	/// cross `gas_limit`. Every gas charge (the injected `gas` function as well as the
+	/// runtime-side costs below) funnels through here so there is a single overflow-checked
+	/// point of truth for `gas_left()`; only `refund()` lowers the counter.
+	fn charge(&mut self, amount: u64) -> Result<(), Error> {
+		self.gas_counter = checked_charge(self.gas_counter, self.gas_limit, amount)?;
+		Ok(())
+	}
+
+	fn charge_or_trap(&mut self, amount: u64) -> Result<(), interpreter::Error> {
+		self.charge(amount)
+			.map_err(|_| interpreter::Error::Trap(format!("Gas exceeds limits of {}", self.gas_limit)))
+	}
+
+	/// Give back gas that was forwarded to a sub-call (`do_call`/`create`) but not spent.
+	/// The counterpart to `charge()` -- every `gas_counter` mutation goes through one of
+	/// these two so `gas_left()` never drifts out of sync.
+	fn refund(&mut self, amount: u64) {
+		self.gas_counter = checked_refund(self.gas_counter, amount);
+	}
+
 	pub fn storage_write(&mut self, context: interpreter::CallerContext) 
 		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
 	{
@@ -89,6 +193,8 @@ impl<'a> Runtime<'a> {
 		let key = self.pop_h256(&mut context)?;
 		trace!(target: "wasm", "storage_write: value {} at @{}", &val, &key);
 
+		self.charge_or_trap(self.costs.sstore as u64)?;
+
 		// todo: return a runtime error contract can handle or as it is now - general failure?
 		self.ext.set_storage(key, val)
 			.map_err(|_| interpreter::Error::Trap("Storage update error".to_owned()))?;
@@ -101,7 +207,9 @@ impl<'a> Runtime<'a> {
 	{
 		let mut context = context;
 		let val_ptr = context.value_stack.pop_as::<i32>()?;
-		let key = self.pop_h256(&mut context)?;		
+		let key = self.pop_h256(&mut context)?;
+
+		self.charge_or_trap(self.costs.sload as u64)?;
 
 		// todo: return a runtime error contract can handle or as it is now - general failure?
 		let val = self.ext.storage_at(&key)
@@ -124,33 +232,257 @@ impl<'a> Runtime<'a> {
 		Ok(None)
 	}
 
-	pub fn malloc(&mut self, context: interpreter::CallerContext) 
+	pub fn malloc(&mut self, context: interpreter::CallerContext)
 		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
 	{
+		let mut context = context;
 		let amount = context.value_stack.pop_as::<i32>()? as u32;
-		let previous_top = self.dynamic_top;
-		self.dynamic_top = previous_top + amount;
+		let previous_top = self.alloc(amount)
+			.map_err(|_| interpreter::Error::Trap("Allocator error".to_owned()))?;
 		Ok(Some((previous_top as i32).into()))
 	}
 
 	pub fn alloc(&mut self, amount: u32) -> Result<u32, Error> {
+		self.charge(amount as u64 * self.costs.alloc as u64)?;
+
 		let previous_top = self.dynamic_top;
-		self.dynamic_top = previous_top + amount;
-		Ok(previous_top.into())
+		let new_top = checked_alloc_top(previous_top, amount, self.memory.size())?;
+
+		self.dynamic_top = new_top;
+		Ok(previous_top)
 	}
 
-	fn gas(&mut self, context: interpreter::CallerContext) 
-		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error> 
+	fn gas(&mut self, context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		let mut context = context;
+		let amount = context.value_stack.pop_as::<i32>()? as u64;
+		self.charge_or_trap(amount)?;
+		Ok(None)
+	}
+
+	/// Invoke another contract, forwarding `gas` and (for `Call`) a value. The input region
+	/// is read straight out of wasm memory; on return the output bytes are copied back in
+	/// (allocated via `alloc`) and the descriptor's `return_ptr`/`return_len` fields are
+	/// updated to point at them. Returns `0` on success and a nonzero status on revert so
+	/// the contract can branch; any gas the sub-call didn't use is folded back into
+	/// `gas_counter` so `gas_left()` stays accurate.
+	fn do_call(
+		&mut self,
+		call_type: CallType,
+		context: interpreter::CallerContext,
+	) -> Result<Option<interpreter::RuntimeValue>, interpreter::Error> {
+		let mut context = context;
+
+		let descriptor_ptr = context.value_stack.pop_as::<i32>()? as u32;
+		let input_len = context.value_stack.pop_as::<i32>()? as u32;
+		let input_ptr = context.value_stack.pop_as::<i32>()? as u32;
+		let value = match call_type {
+			CallType::Call => Some(self.pop_h256(&mut context)?),
+			_ => None,
+		};
+		let address = self.pop_address(&mut context)?;
+		let gas = context.value_stack.pop_as::<i64>()? as u64;
+
+		let input = self.memory.get(input_ptr, input_len as usize)?;
+
+		let gas_left = self.gas_left()
+			.map_err(|_| interpreter::Error::Trap("Invalid gas state".to_owned()))?;
+		let forwarded_gas = ::std::cmp::min(gas, gas_left);
+		self.charge_or_trap(forwarded_gas)?;
+
+		let (gas_used, output, reverted) = self.ext.call(
+			U256::from(forwarded_gas),
+			&address,
+			value.map(|v| U256::from(v)),
+			&input,
+			call_type,
+		).map_err(|_| interpreter::Error::Trap("Call error".to_owned()))?;
+
+		// fold back whatever gas the sub-call didn't actually spend
+		let gas_used = ::std::cmp::min(gas_used.low_u64(), forwarded_gas);
+		self.refund(forwarded_gas - gas_used);
+
+		let output_ptr = self.alloc(output.len() as u32)?;
+		self.memory.set(output_ptr, &output)?;
+
+		let mut return_slots = [0u8; 8];
+		LittleEndian::write_u32(&mut return_slots[0..4], output_ptr);
+		LittleEndian::write_u32(&mut return_slots[4..8], output.len() as u32);
+		let return_slots_ptr = descriptor_ptr.checked_add(8)
+			.ok_or_else(|| interpreter::Error::Trap("Memory access violation".to_owned()))?;
+		self.memory.set(return_slots_ptr, &return_slots)?;
+
+		Ok(Some((if reverted { 1i32 } else { 0i32 }).into()))
+	}
+
+	/// Deploy a child contract, mirroring the EVM `CREATE` opcode. Pops a gas amount, an
+	/// endowment value, a `(ptr, len)` code region and an output pointer for the new
+	/// contract's 20-byte address; forwards that gas (capped at what is left) to
+	/// `self.ext.create(...)` and, on success, writes the resulting address to the output
+	/// pointer the same way `write_descriptor` writes its fields. Returns `0` when a
+	/// contract was created and `1` when none was; an error from `self.ext.create` traps.
+	fn create(&mut self, context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		let mut context = context;
+
+		let result_ptr = context.value_stack.pop_as::<i32>()? as u32;
+		let code_len = context.value_stack.pop_as::<i32>()? as u32;
+		let code_ptr = context.value_stack.pop_as::<i32>()? as u32;
+		let endowment = self.pop_h256(&mut context)?;
+		let gas = context.value_stack.pop_as::<i64>()? as u64;
+
+		let code = self.memory.get(code_ptr, code_len as usize)?;
+
+		let gas_left = self.gas_left()
+			.map_err(|_| interpreter::Error::Trap("Invalid gas state".to_owned()))?;
+		let forwarded_gas = ::std::cmp::min(gas, gas_left);
+		self.charge_or_trap(forwarded_gas)?;
+
+		let (gas_used, maybe_address) = self.ext.create(U256::from(forwarded_gas), &U256::from(endowment), &code)
+			.map_err(|_| interpreter::Error::Trap("Create error".to_owned()))?;
+
+		// fold back whatever gas the creation didn't actually spend
+		let gas_used = ::std::cmp::min(gas_used.low_u64(), forwarded_gas);
+		self.refund(forwarded_gas - gas_used);
+
+		match maybe_address {
+			Some(address) => {
+				self.memory.set(result_ptr, &*address)?;
+				Ok(Some(0i32.into()))
+			},
+			None => Ok(Some(1i32.into())),
+		}
+	}
+
+	// Environment/blockchain query host functions below give wasm contracts parity with
+	// the EVM's `NUMBER`/`TIMESTAMP`/`COINBASE`/`BLOCKHASH`/`DIFFICULTY`/`GASLIMIT`/
+	// `BALANCE`/`GAS` opcodes. None of these are free on the EVM side either, so each
+	// charges through `WasmCosts` before touching `self.ext`.
+
+	fn blocknumber(&mut self, _context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		self.charge_or_trap(self.costs.env_info as u64)?;
+		Ok(Some((self.ext.env_info().number as i64).into()))
+	}
+
+	fn timestamp(&mut self, _context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		self.charge_or_trap(self.costs.env_info as u64)?;
+		Ok(Some((self.ext.env_info().timestamp as i64).into()))
+	}
+
+	fn coinbase(&mut self, context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		let mut context = context;
+		let ptr = context.value_stack.pop_as::<i32>()? as u32;
+		self.charge_or_trap(self.costs.env_info as u64)?;
+		self.memory.set(ptr, &*self.ext.env_info().author)?;
+		Ok(None)
+	}
+
+	fn blockhash(&mut self, context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		let mut context = context;
+		let ptr = context.value_stack.pop_as::<i32>()? as u32;
+		let number = context.value_stack.pop_as::<i64>()? as u64;
+
+		self.charge_or_trap(self.costs.blockhash as u64)?;
+
+		let hash = self.ext.blockhash(&U256::from(number));
+		self.memory.set(ptr, &*hash)?;
+
+		Ok(None)
+	}
+
+	fn difficulty(&mut self, context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		let mut context = context;
+		let ptr = context.value_stack.pop_as::<i32>()? as u32;
+		self.charge_or_trap(self.costs.env_info as u64)?;
+		self.memory.set(ptr, &*H256::from(self.ext.env_info().difficulty))?;
+		Ok(None)
+	}
+
+	fn block_gas_limit(&mut self, context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
 	{
-		let prev = self.gas_counter;
-		let update = context.value_stack.pop_as::<i32>()? as u64;
-		if prev + update > self.gas_limit {
-			// exceeds gas
-			Err(interpreter::Error::Trap(format!("Gas exceeds limits of {}", self.gas_limit)))
-		} else {
-			self.gas_counter = prev + update;
-			Ok(None)
+		let mut context = context;
+		let ptr = context.value_stack.pop_as::<i32>()? as u32;
+		self.charge_or_trap(self.costs.env_info as u64)?;
+		self.memory.set(ptr, &*H256::from(self.ext.env_info().gas_limit))?;
+		Ok(None)
+	}
+
+	fn balance(&mut self, context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		let mut context = context;
+		let result_ptr = context.value_stack.pop_as::<i32>()? as u32;
+		let address = self.pop_address(&mut context)?;
+
+		self.charge_or_trap(self.costs.balance as u64)?;
+
+		let balance = self.ext.balance(&address)
+			.map_err(|_| interpreter::Error::Trap("Balance query error".to_owned()))?;
+
+		self.memory.set(result_ptr, &*H256::from(balance))?;
+
+		Ok(None)
+	}
+
+	fn gasleft(&mut self, _context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		self.charge_or_trap(self.costs.env_info as u64)?;
+		let gas_left = self.gas_left()
+			.map_err(|_| interpreter::Error::Trap("Invalid gas state".to_owned()))?;
+		Ok(Some((gas_left as i64).into()))
+	}
+
+	/// Emit a log entry, giving wasm contracts parity with the EVM `LOG0`-`LOG4` opcodes.
+	/// Pops a topics count (0-4), a pointer to a packed array of 32-byte topic hashes and a
+	/// `(data_ptr, data_len)` region, then forwards them to `self.ext.log`. Traps on a
+	/// malformed topic count or a memory-access violation.
+	fn elog(&mut self, context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		let mut context = context;
+
+		let data_len = context.value_stack.pop_as::<i32>()? as u32;
+		let data_ptr = context.value_stack.pop_as::<i32>()? as u32;
+		let topics_ptr = context.value_stack.pop_as::<i32>()? as u32;
+		let topics_count = context.value_stack.pop_as::<i32>()? as u32;
+
+		if topics_count > 4 {
+			return Err(interpreter::Error::Trap("Too many topics in _elog call".to_owned()));
+		}
+
+		self.charge_or_trap(topics_count as u64 * self.costs.log_topic as u64)?;
+		self.charge_or_trap(data_len as u64 * self.costs.log_data as u64)?;
+
+		let mut topics = Vec::with_capacity(topics_count as usize);
+		for i in 0..topics_count {
+			let offset = i.checked_mul(32)
+				.and_then(|o| topics_ptr.checked_add(o))
+				.ok_or_else(|| interpreter::Error::Trap("Memory access violation".to_owned()))?;
+			let ptr = WasmPtr::from_i32(offset as i32)
+				.map_err(|_| interpreter::Error::Trap("Memory access violation".to_owned()))?;
+			topics.push(self.h256_at(ptr)?);
 		}
+
+		let data = self.memory.get(data_ptr, data_len as usize)?;
+
+		self.ext.log(topics, &data)
+			.map_err(|_| interpreter::Error::Trap("Log error".to_owned()))?;
+
+		Ok(None)
 	}
 
 	fn h256_at(&self, ptr: WasmPtr) -> Result<H256, interpreter::Error> {
@@ -195,6 +527,8 @@ impl<'a> Runtime<'a> {
 		let args_len = call_args.len();
 		let args_ptr = self.alloc(args_len)?;
 
+		self.charge((16 + args_len as u64) * self.costs.mem_copy as u64)?;
+
 		// write call descriptor
 		// call descriptor is [args_ptr, args_len, return_ptr, return_len]
 		//   all are 4 byte length, last 2 are zeroed
@@ -209,11 +543,32 @@ impl<'a> Runtime<'a> {
 		self.memory.set(args_ptr+40, &call_args.origin)?;
 		self.memory.set(args_ptr+60, &call_args.value)?;
 		self.memory.set(args_ptr+92, &call_args.data)?;
-		
+
 		Ok(d_ptr.into())
 	}
 
-	fn debug_log(&mut self, context: interpreter::CallerContext) 
+	/// Set the contract's return payload. Shared by `_return` and `_revert`, which only
+	/// differ in whether they also flag the call for a storage rollback.
+	fn set_result(&mut self, revert: bool, context: interpreter::CallerContext)
+		-> Result<Option<interpreter::RuntimeValue>, interpreter::Error>
+	{
+		let mut context = context;
+		let len = context.value_stack.pop_as::<i32>()? as u32;
+		let ptr = context.value_stack.pop_as::<i32>()? as u32;
+
+		self.result = self.memory.get(ptr, len as usize)?;
+		self.should_revert = revert;
+
+		Ok(None)
+	}
+
+	/// Consume the runtime, handing back the bytes a `_return`/`_revert` call set and
+	/// whether the caller should roll back storage changes made during execution.
+	pub fn into_result(self) -> (Vec<u8>, bool) {
+		(self.result, self.should_revert)
+	}
+
+	fn debug_log(&mut self, context: interpreter::CallerContext)
 			-> Result<Option<interpreter::RuntimeValue>, interpreter::Error> 
 	{
 		let msg_len = context.value_stack.pop_as::<i32>()? as u32;
@@ -259,6 +614,51 @@ impl<'a> interpreter::UserFunctionExecutor for Runtime<'a> {
 			"_suicide" => {
 				self.suicide(context)
 			},
+			"_ccall" => {
+				self.do_call(CallType::Call, context)
+			},
+			"_dcall" => {
+				self.do_call(CallType::DelegateCall, context)
+			},
+			"_scall" => {
+				self.do_call(CallType::StaticCall, context)
+			},
+			"_create" => {
+				self.create(context)
+			},
+			"_return" => {
+				self.set_result(false, context)
+			},
+			"_revert" => {
+				self.set_result(true, context)
+			},
+			"_blocknumber" => {
+				self.blocknumber(context)
+			},
+			"_timestamp" => {
+				self.timestamp(context)
+			},
+			"_coinbase" => {
+				self.coinbase(context)
+			},
+			"_blockhash" => {
+				self.blockhash(context)
+			},
+			"_difficulty" => {
+				self.difficulty(context)
+			},
+			"_gaslimit" => {
+				self.block_gas_limit(context)
+			},
+			"_balance" => {
+				self.balance(context)
+			},
+			"_gasleft" => {
+				self.gasleft(context)
+			},
+			"_elog" => {
+				self.elog(context)
+			},
 			"_debug" => {
 				self.debug_log(context)
 			},
@@ -271,4 +671,63 @@ impl<'a> interpreter::UserFunctionExecutor for Runtime<'a> {
 			}
 		}
 	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{checked_charge, checked_refund, checked_alloc_top, Error};
+
+	#[test]
+	fn charge_accumulates_under_limit() {
+		let next = checked_charge(10, 100, 20).unwrap();
+		assert_eq!(next, 30);
+	}
+
+	#[test]
+	fn charge_errors_at_the_limit_boundary() {
+		assert!(checked_charge(90, 100, 10).is_ok());
+		match checked_charge(91, 100, 10) {
+			Err(Error::GasLimit) => (),
+			other => panic!("expected GasLimit, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn charge_does_not_silently_wrap_on_overflow() {
+		match checked_charge(u64::max_value() - 1, u64::max_value(), 10) {
+			Err(Error::GasLimit) => (),
+			other => panic!("expected GasLimit, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn refund_folds_unused_sub_call_gas_back_in() {
+		assert_eq!(checked_refund(100, 40), 60);
+	}
+
+	#[test]
+	fn refund_saturates_instead_of_underflowing() {
+		assert_eq!(checked_refund(10, 40), 0);
+	}
+
+	#[test]
+	fn alloc_top_grows_within_memory() {
+		assert_eq!(checked_alloc_top(16, 16, 64).unwrap(), 32);
+	}
+
+	#[test]
+	fn alloc_top_errors_past_memory_size() {
+		match checked_alloc_top(48, 32, 64) {
+			Err(Error::Allocator) => (),
+			other => panic!("expected Allocator, got {:?}", other),
+		}
+	}
+
+	#[test]
+	fn alloc_top_does_not_silently_wrap_on_overflow() {
+		match checked_alloc_top(u32::max_value() - 4, 16, usize::max_value()) {
+			Err(Error::Allocator) => (),
+			other => panic!("expected Allocator, got {:?}", other),
+		}
+	}
 }
\ No newline at end of file